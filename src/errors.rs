@@ -0,0 +1,115 @@
+use std::convert::Infallible;
+
+use ethers::abi::{self, ParamType};
+use ethers::types::Bytes;
+use revm::interpreter::InstructionResult;
+use serde::Serialize;
+use warp::{http::StatusCode, reject::Reject, Rejection, Reply};
+
+/// Standard Solidity `Error(string)` selector, used for `require`/`revert("...")`.
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// Standard Solidity `Panic(uint256)` selector, used for compiler-inserted panics
+/// (overflow, assert, out-of-bounds, ...).
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Best-effort decode of a revert's return data into a human-readable reason, falling
+/// back to the raw hex when the data doesn't match a selector we understand.
+pub fn decode_revert(data: &[u8]) -> String {
+    if data.len() < 4 {
+        return Bytes::from(data.to_vec()).to_string();
+    }
+    let (selector, rest) = data.split_at(4);
+
+    if selector == ERROR_SELECTOR {
+        if let Ok(tokens) = abi::decode(&[ParamType::String], rest) {
+            if let Some(abi::Token::String(reason)) = tokens.into_iter().next() {
+                return reason;
+            }
+        }
+    } else if selector == PANIC_SELECTOR {
+        if let Ok(tokens) = abi::decode(&[ParamType::Uint(256)], rest) {
+            if let Some(abi::Token::Uint(code)) = tokens.into_iter().next() {
+                return format!("panic: code {code:#x}");
+            }
+        }
+    }
+
+    Bytes::from(data.to_vec()).to_string()
+}
+
+/// Why a simulated call failed to produce a usable result.
+///
+/// A *reverted* call is not itself an error — `CallRawResult::success` is `false` and the
+/// caller gets back return data and an `exit_reason` like any other result. This enum
+/// separates three kinds of failure: a revert, an EVM halt that isn't a revert, and a
+/// failure to read state from the fork, which means the forked node itself is unreachable
+/// or corrupt rather than that the simulation failed. `Evm` itself only produces
+/// `StateFetch`: reverts and halts of a simulated call, including during access-list
+/// generation, come back as a `CallRawResult` with `success: false`.
+#[derive(Debug)]
+pub enum SimulationError {
+    /// Execution reverted, with the decoded revert reason (when one could be decoded) and
+    /// the raw return data.
+    Revert { reason: String, data: Bytes },
+    /// Execution halted for a reason other than a revert (out of gas, invalid opcode,
+    /// stack over/underflow, etc).
+    Halt(InstructionResult),
+    /// Reading an account, storage slot, or code from the backend's `DatabaseRef` failed,
+    /// most commonly because the RPC endpoint backing the fork is unreachable.
+    StateFetch(String),
+}
+
+impl std::fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SimulationError::Revert { reason, .. } => write!(f, "execution reverted: {reason}"),
+            SimulationError::Halt(reason) => write!(f, "execution halted: {reason:?}"),
+            SimulationError::StateFetch(reason) => {
+                write!(f, "failed to fetch state from fork: {reason}")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct EvmError(pub SimulationError);
+
+impl Reject for EvmError {}
+
+#[derive(Debug)]
+pub struct OverrideError(pub SimulationError);
+
+impl Reject for OverrideError {}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+pub async fn handle_rejection(err: Rejection) -> Result<impl Reply, Infallible> {
+    let (status, message) = if err.is_not_found() {
+        (StatusCode::NOT_FOUND, "not found".to_string())
+    } else if let Some(EvmError(inner)) = err.find() {
+        (status_for(inner), inner.to_string())
+    } else if let Some(OverrideError(inner)) = err.find() {
+        (status_for(inner), inner.to_string())
+    } else {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal server error".to_string(),
+        )
+    };
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&ErrorBody { error: message }),
+        status,
+    ))
+}
+
+fn status_for(err: &SimulationError) -> StatusCode {
+    match err {
+        SimulationError::Revert { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+        SimulationError::Halt(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        SimulationError::StateFetch(_) => StatusCode::BAD_GATEWAY,
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use ethers::abi::{Address, Hash, Uint};
 use ethers::core::types::Log;
@@ -10,15 +10,24 @@ use foundry_evm::executor::{opts::EvmOpts, Backend, ExecutorBuilder};
 use foundry_evm::trace::identifier::{EtherscanIdentifier, SignaturesIdentifier};
 use foundry_evm::trace::node::CallTraceNode;
 use foundry_evm::trace::{CallTraceArena, CallTraceDecoder, CallTraceDecoderBuilder};
-use foundry_evm::utils::{h160_to_b160, u256_to_ru256};
+use foundry_evm::utils::{b160_to_h160, h160_to_b160, u256_to_ru256};
 use revm::db::DatabaseRef;
 use revm::interpreter::InstructionResult;
-use revm::primitives::{Account, Bytecode, Env, StorageSlot};
+use revm::primitives::{Account, AccountInfo, Bytecode, Env, StorageSlot, TransactTo, B160};
 use revm::DatabaseCommit;
+use revm::EVM;
 
-use crate::errors::{EvmError, OverrideError};
+use crate::errors::{EvmError, OverrideError, SimulationError};
 use crate::simulation::CallTrace;
 
+mod inspectors;
+mod state_diff;
+
+use inspectors::access_list::precompile_addresses;
+use inspectors::stack::{InspectorConfig, InspectorStack, StructLogConfig};
+use inspectors::struct_log::StructLog;
+use state_diff::{build_state_diff, StateDiff};
+
 #[derive(Debug, Clone)]
 pub struct CallRawRequest {
     pub from: Address,
@@ -26,7 +35,18 @@ pub struct CallRawRequest {
     pub value: Option<Uint>,
     pub data: Option<Bytes>,
     pub access_list: Option<AccessList>,
+    pub generate_access_list: bool,
     pub format_trace: bool,
+    /// Set to `Some("structLog")` to collect a geth-style `debug_traceCall` opcode trace
+    /// in `CallRawResult::struct_logs`. No other tracer types are implemented yet.
+    pub trace_type: Option<String>,
+    pub disable_stack: bool,
+    pub disable_memory: bool,
+    pub disable_storage: bool,
+    /// Only consulted by `call_raw_committing`: when set, `CallRawResult::state_diff` is
+    /// populated with the pre/post balance, nonce, code hash, and changed storage slots
+    /// for every account the call touched.
+    pub state_diff: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -39,6 +59,16 @@ pub struct CallRawResult {
     pub exit_reason: InstructionResult,
     pub return_data: Bytes,
     pub formatted_trace: Option<String>,
+    /// Present when `CallRawRequest::generate_access_list` was set. `gas_used` on this
+    /// result already reflects the cost of the call once the list is attached, so callers
+    /// can directly compare it against a run with `access_list: None` to see whether
+    /// attaching the list is worth it.
+    pub access_list: Option<AccessList>,
+    /// Present when `CallRawRequest::trace_type` was `"structLog"`: one entry per opcode
+    /// executed, geth's `debug_traceCall` format.
+    pub struct_logs: Option<Vec<StructLog>>,
+    /// Present when `CallRawRequest::state_diff` was set on a `call_raw_committing` call.
+    pub state_diff: Option<StateDiff>,
 }
 
 impl From<CallTraceNode> for CallTrace {
@@ -62,6 +92,11 @@ pub struct Evm {
     executor: Executor,
     decoder: CallTraceDecoder,
     etherscan_identifier: Option<EtherscanIdentifier>,
+    /// Diagnostics every call should collect unless `CallRawRequest` overrides them.
+    /// `tracing` is consumed once at construction time (it configures the executor
+    /// itself); `access_list`/`struct_log` are merged with the per-call flags in
+    /// `run_inspectors` on every `call_raw`/`call_raw_committing`.
+    default_inspector_config: InspectorConfig,
 }
 
 impl Evm {
@@ -69,7 +104,7 @@ impl Evm {
         env: Option<Env>,
         fork_url: String,
         fork_block_number: Option<u64>,
-        tracing: bool,
+        inspector_config: InspectorConfig,
         etherscan_key: Option<String>,
     ) -> Self {
         let evm_opts = EvmOpts {
@@ -95,8 +130,7 @@ impl Evm {
 
         let db = Backend::spawn(Some(fork_opts.clone()));
 
-        let mut builder = ExecutorBuilder::default()
-            .set_tracing(tracing);
+        let mut builder = ExecutorBuilder::default().set_tracing(inspector_config.tracing);
 
         if let Some(env) = env {
             builder = builder.with_config(env);
@@ -125,11 +159,17 @@ impl Evm {
             executor,
             decoder,
             etherscan_identifier,
+            default_inspector_config: inspector_config,
         }
     }
 
-    pub async fn call_raw(&mut self, call: CallRawRequest) -> Result<CallRawResult, EvmError> {
-        self.set_access_list(call.access_list);
+    pub async fn call_raw(&mut self, mut call: CallRawRequest) -> Result<CallRawResult, EvmError> {
+        let (access_list, struct_logs, _touched) = self.run_inspectors(&call)?;
+        if let Some(access_list) = &access_list {
+            call.access_list = Some(access_list.clone());
+        }
+
+        self.set_access_list(&call.access_list);
         let res = self
             .executor
             .call_raw(
@@ -138,10 +178,7 @@ impl Evm {
                 call.data.unwrap_or_default().0,
                 call.value.unwrap_or_default(),
             )
-            .map_err(|err| {
-                dbg!(&err);
-                EvmError(err)
-            })?;
+            .map_err(|err| EvmError(SimulationError::StateFetch(format!("{err:?}"))))?;
 
         let formatted_trace = if call.format_trace {
             let mut output = String::new();
@@ -166,9 +203,95 @@ impl Evm {
             exit_reason: res.exit_reason,
             return_data: Bytes(res.result),
             formatted_trace,
+            access_list,
+            struct_logs,
+            state_diff: None,
         })
     }
 
+    /// Runs `call` once through a single composed [`InspectorStack`] built from whichever
+    /// diagnostics `call` requests (access list, struct log, ...), so adding another
+    /// diagnostic never costs another re-execution of the call. This is a read-only dry
+    /// run against the current backend state; it does not commit anything. It runs under
+    /// `call`'s own access list, except when generating one, where it starts from an empty
+    /// list.
+    ///
+    /// A revert or halt here is an execution-level outcome of `call` itself, not a failure
+    /// of this dry run — the inspectors already recorded whatever they saw up to that
+    /// point, and the real `call_raw`/`call_raw_committing` execution below is what decides
+    /// `CallRawResult::success`/`exit_reason`. So this only ever returns `Err` for a
+    /// genuine failure to read state from the backend (`StateFetch`); the collected
+    /// diagnostics are returned as-is, complete or not, for every other outcome.
+    ///
+    /// Also returns the set of addresses the call touched, so a caller that separately
+    /// needs a pre-commit account snapshot (`call_raw_committing`'s `state_diff`) can reuse
+    /// it instead of paying for a second dry run of the same call. This is only returned
+    /// when `config.access_list` is *not* set, i.e. when this dry run ran under `call`'s
+    /// real access list: an access-list-generation pass forces an empty one (see below),
+    /// which can make a gas-sensitive call touch a different set of addresses than it
+    /// would for real, so that touched set isn't trustworthy for anything but the access
+    /// list it was computed for.
+    fn run_inspectors(
+        &mut self,
+        call: &CallRawRequest,
+    ) -> Result<(Option<AccessList>, Option<Vec<StructLog>>, Option<HashSet<Address>>), EvmError>
+    {
+        let config = InspectorConfig {
+            tracing: false,
+            access_list: call.generate_access_list || self.default_inspector_config.access_list,
+            struct_log: (call.trace_type.as_deref() == Some("structLog"))
+                .then(|| StructLogConfig {
+                    disable_stack: call.disable_stack,
+                    disable_memory: call.disable_memory,
+                    disable_storage: call.disable_storage,
+                })
+                .or_else(|| self.default_inspector_config.struct_log.clone()),
+        };
+
+        if !config.access_list && config.struct_log.is_none() {
+            return Ok((None, None, None));
+        }
+
+        let excluded: HashSet<Address> = precompile_addresses()
+            .chain(std::iter::once(call.from))
+            .collect();
+        let mut stack = InspectorStack::new(&config, excluded);
+
+        let mut env = self.executor.env().clone();
+        env.tx.caller = h160_to_b160(call.from);
+        env.tx.transact_to = TransactTo::Call(h160_to_b160(call.to));
+        env.tx.data = call.data.clone().unwrap_or_default().0;
+        env.tx.value = u256_to_ru256(call.value.unwrap_or_default());
+        // `self.executor.env()` may still carry whatever access list a previous call left
+        // installed (`set_access_list` mutates it in place), so always start from `call`'s
+        // own access list rather than just overriding it in the generation case below.
+        env.tx.access_list = revm_access_list(&call.access_list);
+        if config.access_list {
+            // Generating an access list requires the dry run to see every access as cold,
+            // so it has to start from an empty one.
+            env.tx.access_list = Vec::new();
+        }
+
+        let mut evm = EVM::new();
+        evm.env = env;
+        evm.database(self.executor.backend().clone());
+
+        let result_and_state = evm
+            .inspect_ref(&mut stack)
+            .map_err(|err| EvmError(SimulationError::StateFetch(format!("{err:?}"))))?;
+
+        let touched = (!config.access_list).then(|| {
+            result_and_state
+                .state
+                .keys()
+                .map(|address| b160_to_h160(*address))
+                .collect()
+        });
+        let (access_list, struct_logs) = stack.into_results();
+
+        Ok((access_list, struct_logs, touched))
+    }
+
     pub fn override_account(
         &mut self,
         address: Address,
@@ -183,7 +306,7 @@ impl Evm {
                 .executor
                 .backend()
                 .basic(address)
-                .map_err(|_| OverrideError)?
+                .map_err(|err| OverrideError(SimulationError::StateFetch(format!("{err:?}"))))?
                 .unwrap_or_default(),
             ..Account::new_not_existing()
         };
@@ -221,9 +344,24 @@ impl Evm {
 
     pub async fn call_raw_committing(
         &mut self,
-        call: CallRawRequest,
+        mut call: CallRawRequest,
     ) -> Result<CallRawResult, EvmError> {
-        self.set_access_list(call.access_list);
+        let (access_list, struct_logs, touched) = self.run_inspectors(&call)?;
+        if let Some(access_list) = &access_list {
+            call.access_list = Some(access_list.clone());
+        }
+
+        // Apply the final access list (caller-supplied or just-generated) before any dry
+        // run that discovers the pre-commit account snapshot below, so that snapshot
+        // reflects the same access list the real committing call is about to use.
+        self.set_access_list(&call.access_list);
+
+        let pre_state = if call.state_diff {
+            Some(self.snapshot_accounts(touched, &call)?)
+        } else {
+            None
+        };
+
         let res = self
             .executor
             .call_raw_committing(
@@ -232,10 +370,7 @@ impl Evm {
                 call.data.unwrap_or_default().0,
                 call.value.unwrap_or_default(),
             )
-            .map_err(|err| {
-                dbg!(&err);
-                EvmError(err)
-            })?;
+            .map_err(|err| EvmError(SimulationError::StateFetch(format!("{err:?}"))))?;
 
         let formatted_trace = if call.format_trace {
             let mut output = String::new();
@@ -251,6 +386,13 @@ impl Evm {
             None
         };
 
+        let state_diff = pre_state.map(|pre| {
+            build_state_diff(
+                &pre,
+                res.state_changeset.as_ref().unwrap_or(&HashMap::new()),
+            )
+        });
+
         Ok(CallRawResult {
             gas_used: res.gas_used,
             block_number: res.env.block.number.to(),
@@ -260,9 +402,64 @@ impl Evm {
             exit_reason: res.exit_reason,
             return_data: Bytes(res.result),
             formatted_trace,
+            access_list,
+            struct_logs,
+            state_diff,
         })
     }
 
+    /// Snapshots the `AccountInfo` of every address a `call_raw_committing` call is about
+    /// to touch, read from the backend before anything commits.
+    ///
+    /// `touched` is the address set `run_inspectors` already discovered while generating a
+    /// struct log for this same call under `call`'s real access list; when present, it's
+    /// reused here instead of paying for a second dry run. `run_inspectors` withholds it
+    /// instead (`None`) whenever it ran under a forced-empty access list for access-list
+    /// generation, since that can touch a different set of addresses than the real call
+    /// would; this runs its own single non-committing dry run under the real access list
+    /// to discover it instead, which revm already populates with a `state_changeset`
+    /// without mutating backend state.
+    fn snapshot_accounts(
+        &mut self,
+        touched: Option<HashSet<Address>>,
+        call: &CallRawRequest,
+    ) -> Result<HashMap<Address, AccountInfo>, EvmError> {
+        let touched = match touched {
+            Some(touched) => touched,
+            None => {
+                let dry_run = self
+                    .executor
+                    .call_raw(
+                        call.from,
+                        call.to,
+                        call.data.clone().unwrap_or_default().0,
+                        call.value.unwrap_or_default(),
+                    )
+                    .map_err(|err| EvmError(SimulationError::StateFetch(format!("{err:?}"))))?;
+
+                dry_run
+                    .state_changeset
+                    .unwrap_or_default()
+                    .into_keys()
+                    .map(b160_to_h160)
+                    .collect()
+            }
+        };
+
+        touched
+            .into_iter()
+            .map(|address| {
+                let info = self
+                    .executor
+                    .backend()
+                    .basic(h160_to_b160(address))
+                    .map_err(|err| EvmError(SimulationError::StateFetch(format!("{err:?}"))))?
+                    .unwrap_or_default();
+                Ok((address, info))
+            })
+            .collect()
+    }
+
     pub async fn set_block(&mut self, number: u64) -> Result<(), EvmError> {
         self.executor.env_mut().block.number = Uint::from(number).into();
         Ok(())
@@ -285,20 +482,27 @@ impl Evm {
         self.executor.env().cfg.chain_id.into()
     }
 
-    fn set_access_list(&mut self, access_list: Option<AccessList>) {
-        self.executor.env_mut().tx.access_list = access_list
-            .unwrap_or_default()
-            .0
-            .into_iter()
-            .map(|item| {
-                (
-                    h160_to_b160(item.address),
-                    item.storage_keys
-                        .into_iter()
-                        .map(|key| u256_to_ru256(Uint::from_big_endian(key.as_bytes())))
-                        .collect(),
-                )
-            })
-            .collect();
+    fn set_access_list(&mut self, access_list: &Option<AccessList>) {
+        self.executor.env_mut().tx.access_list = revm_access_list(access_list);
     }
 }
+
+/// Converts an EIP-2930 access list into the `(address, storage keys)` pairs revm's
+/// `tx.access_list` expects.
+fn revm_access_list(access_list: &Option<AccessList>) -> Vec<(B160, Vec<revm::primitives::U256>)> {
+    access_list
+        .clone()
+        .unwrap_or_default()
+        .0
+        .into_iter()
+        .map(|item| {
+            (
+                h160_to_b160(item.address),
+                item.storage_keys
+                    .into_iter()
+                    .map(|key| u256_to_ru256(Uint::from_big_endian(key.as_bytes())))
+                    .collect(),
+            )
+        })
+        .collect()
+}
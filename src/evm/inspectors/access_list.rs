@@ -0,0 +1,159 @@
+use std::collections::{HashMap, HashSet};
+
+use ethers::abi::Address;
+use ethers::types::transaction::eip2930::{AccessList, AccessListItem};
+use ethers::types::H256;
+use foundry_evm::utils::b160_to_h160;
+use revm::interpreter::{opcode, InstructionResult, Interpreter};
+use revm::primitives::U256;
+use revm::{Database, EVMData, Inspector};
+
+/// The addresses that are always considered "warm" and therefore must never show up in a
+/// generated access list: the standard Ethereum precompiles (`0x01`..=`0x09`).
+pub fn precompile_addresses() -> impl Iterator<Item = Address> {
+    (1u64..=9).map(|byte| Address::from_low_u64_be(byte))
+}
+
+/// Collects the set of storage slots and accounts touched during a call, per EIP-2930.
+///
+/// Every `SLOAD`/`SSTORE` records the slot on the currently executing contract, and every
+/// account-touching opcode (`CALL`, `DELEGATECALL`, `STATICCALL`, `CALLCODE`, `BALANCE`,
+/// `EXTCODESIZE`, `EXTCODECOPY`, `EXTCODEHASH`, `SELFDESTRUCT`) records the target address.
+/// Addresses passed in via `excluded` (the sender and the precompiles) are dropped from the
+/// resulting list, matching the foundry access-list tracer this mirrors.
+#[derive(Debug, Default)]
+pub struct AccessListInspector {
+    excluded: HashSet<Address>,
+    access_list: HashMap<Address, HashSet<H256>>,
+}
+
+impl AccessListInspector {
+    pub fn new(excluded: HashSet<Address>) -> Self {
+        Self {
+            excluded,
+            access_list: HashMap::new(),
+        }
+    }
+
+    pub fn into_access_list(self) -> AccessList {
+        AccessList(
+            self.access_list
+                .into_iter()
+                .map(|(address, slots)| AccessListItem {
+                    address,
+                    storage_keys: slots.into_iter().collect(),
+                })
+                .collect(),
+        )
+    }
+
+    fn record_address(&mut self, address: Address) {
+        if !self.excluded.contains(&address) {
+            self.access_list.entry(address).or_default();
+        }
+    }
+
+    fn record_slot(&mut self, address: Address, slot: H256) {
+        if !self.excluded.contains(&address) {
+            self.access_list.entry(address).or_default().insert(slot);
+        }
+    }
+}
+
+fn address_from_word(word: U256) -> Address {
+    Address::from_slice(&word.to_be_bytes::<32>()[12..])
+}
+
+/// Which stack slot holds the target address for an account-touching opcode this
+/// inspector tracks, `None` for opcodes it doesn't care about. The `CALL`-family opcodes
+/// push `gas` above the address, so the address sits one slot deeper than it does for the
+/// `EXTCODE*`/`BALANCE`/`SELFDESTRUCT` family, which have nothing above it.
+fn address_stack_offset(op: u8) -> Option<usize> {
+    match op {
+        opcode::EXTCODECOPY
+        | opcode::EXTCODEHASH
+        | opcode::EXTCODESIZE
+        | opcode::BALANCE
+        | opcode::SELFDESTRUCT => Some(0),
+        opcode::DELEGATECALL | opcode::CALL | opcode::STATICCALL | opcode::CALLCODE => Some(1),
+        _ => None,
+    }
+}
+
+impl<DB: Database> Inspector<DB> for AccessListInspector {
+    fn step(
+        &mut self,
+        interpreter: &mut Interpreter,
+        _data: &mut EVMData<'_, DB>,
+    ) -> InstructionResult {
+        let pc = interpreter.program_counter();
+        let Some(op) = interpreter.contract.bytecode.bytecode().get(pc).copied() else {
+            return InstructionResult::Continue;
+        };
+
+        match op {
+            opcode::SLOAD | opcode::SSTORE => {
+                if let Ok(slot) = interpreter.stack().peek(0) {
+                    let address = b160_to_h160(interpreter.contract.address);
+                    self.record_slot(address, H256::from(slot.to_be_bytes::<32>()));
+                }
+            }
+            _ => {
+                if let Some(offset) = address_stack_offset(op) {
+                    if let Ok(slot) = interpreter.stack().peek(offset) {
+                        self.record_address(address_from_word(slot));
+                    }
+                }
+            }
+        }
+
+        InstructionResult::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn call_family_reads_address_one_slot_below_gas() {
+        for op in [
+            opcode::CALL,
+            opcode::CALLCODE,
+            opcode::DELEGATECALL,
+            opcode::STATICCALL,
+        ] {
+            assert_eq!(address_stack_offset(op), Some(1));
+        }
+    }
+
+    #[test]
+    fn extcode_and_balance_family_reads_address_on_top() {
+        for op in [
+            opcode::EXTCODECOPY,
+            opcode::EXTCODEHASH,
+            opcode::EXTCODESIZE,
+            opcode::BALANCE,
+            opcode::SELFDESTRUCT,
+        ] {
+            assert_eq!(address_stack_offset(op), Some(0));
+        }
+    }
+
+    #[test]
+    fn unrelated_opcodes_are_ignored() {
+        assert_eq!(address_stack_offset(opcode::SLOAD), None);
+        assert_eq!(address_stack_offset(opcode::SSTORE), None);
+        assert_eq!(address_stack_offset(opcode::ADD), None);
+    }
+
+    #[test]
+    fn address_from_word_takes_the_low_20_bytes() {
+        let mut word = [0u8; 32];
+        word[12..].copy_from_slice(&[0xAAu8; 20]);
+
+        let address = address_from_word(U256::from_be_bytes(word));
+
+        assert_eq!(address, Address::from_slice(&[0xAAu8; 20]));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod access_list;
+pub mod stack;
+pub mod struct_log;
@@ -0,0 +1,92 @@
+use std::collections::HashSet;
+
+use ethers::abi::Address;
+use ethers::types::transaction::eip2930::AccessList;
+use revm::interpreter::{InstructionResult, Interpreter};
+use revm::{Database, EVMData, Inspector};
+
+use super::access_list::AccessListInspector;
+use super::struct_log::{StructLog, StructLogInspector};
+
+/// Which diagnostics `Evm` should collect, so a single execution can feed several of them
+/// at once instead of re-running the call once per diagnostic. Passed once to `Evm::new`
+/// to set the defaults for every call: `tracing` is consumed there directly (it drives
+/// foundry's own call tracer and can't vary per call), while `access_list`/`struct_log`
+/// are merged with whatever `CallRawRequest` additionally asks for on each
+/// `call_raw`/`call_raw_committing` — a flag set at construction is honored even if a
+/// given call doesn't separately request it.
+#[derive(Debug, Clone, Default)]
+pub struct InspectorConfig {
+    /// Enable foundry's built-in call tracer (`CallRawResult::trace`/`formatted_trace`).
+    pub tracing: bool,
+    /// Collect an EIP-2930 access list of the accounts/slots the call touches.
+    pub access_list: bool,
+    /// Collect a geth-style opcode-by-opcode `structLog` trace.
+    pub struct_log: Option<StructLogConfig>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StructLogConfig {
+    pub disable_stack: bool,
+    pub disable_memory: bool,
+    pub disable_storage: bool,
+}
+
+/// Composes the non-cheatcode diagnostic inspectors this crate defines (access list,
+/// struct log, ...) into one [`Inspector`] so `Evm` can drive all of them in a single
+/// execution, the way foundry stacks its own inspectors.
+#[derive(Default)]
+pub struct InspectorStack {
+    pub access_list: Option<AccessListInspector>,
+    pub struct_log: Option<StructLogInspector>,
+}
+
+impl InspectorStack {
+    pub fn new(config: &InspectorConfig, excluded: HashSet<Address>) -> Self {
+        Self {
+            access_list: config
+                .access_list
+                .then(|| AccessListInspector::new(excluded)),
+            struct_log: config.struct_log.as_ref().map(|c| {
+                StructLogInspector::new(c.disable_stack, c.disable_memory, c.disable_storage)
+            }),
+        }
+    }
+
+    pub fn into_results(self) -> (Option<AccessList>, Option<Vec<StructLog>>) {
+        (
+            self.access_list.map(AccessListInspector::into_access_list),
+            self.struct_log.map(|i| i.logs),
+        )
+    }
+}
+
+impl<DB: Database> Inspector<DB> for InspectorStack {
+    fn step(
+        &mut self,
+        interpreter: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+    ) -> InstructionResult {
+        if let Some(inspector) = &mut self.access_list {
+            inspector.step(interpreter, data);
+        }
+        if let Some(inspector) = &mut self.struct_log {
+            inspector.step(interpreter, data);
+        }
+
+        InstructionResult::Continue
+    }
+
+    fn step_end(
+        &mut self,
+        interpreter: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        eval: InstructionResult,
+    ) -> InstructionResult {
+        if let Some(inspector) = &mut self.struct_log {
+            inspector.step_end(interpreter, data, eval);
+        }
+
+        InstructionResult::Continue
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+
+use ethers::types::H256;
+use revm::interpreter::{opcode, InstructionResult, Interpreter, OpCode};
+use revm::{Database, EVMData, Inspector};
+
+/// A single step of a geth-style `debug_traceCall` `structLog`.
+#[derive(Debug, Clone)]
+pub struct StructLog {
+    pub pc: u64,
+    pub op: String,
+    pub gas: u64,
+    pub gas_cost: u64,
+    pub depth: u64,
+    pub stack: Option<Vec<H256>>,
+    pub memory: Option<Vec<H256>>,
+    pub storage: Option<BTreeMap<H256, H256>>,
+}
+
+/// Records one [`StructLog`] per executed opcode, geth's `debug_traceCall` format.
+///
+/// Stack/memory/storage capture are each individually disable-able since memory capture
+/// in particular is expensive to collect on large calls.
+#[derive(Debug, Default)]
+pub struct StructLogInspector {
+    disable_stack: bool,
+    disable_memory: bool,
+    disable_storage: bool,
+    pub logs: Vec<StructLog>,
+    /// Gas remaining and the index of the log entry pushed by `step`, one slot per call
+    /// depth. `step_end` for a `CALL`-family opcode only fires after the whole sub-call at
+    /// `depth + 1` has returned, by which time a single shared field (or the last log
+    /// entry) would belong to that sub-call's own steps; keeping one slot per depth means
+    /// each depth's `step`/`step_end` pair only ever reads back what it itself wrote.
+    pending_gas: Vec<(u64, usize)>,
+}
+
+impl StructLogInspector {
+    pub fn new(disable_stack: bool, disable_memory: bool, disable_storage: bool) -> Self {
+        Self {
+            disable_stack,
+            disable_memory,
+            disable_storage,
+            logs: Vec::new(),
+            pending_gas: Vec::new(),
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for StructLogInspector {
+    fn step(
+        &mut self,
+        interpreter: &mut Interpreter,
+        _data: &mut EVMData<'_, DB>,
+    ) -> InstructionResult {
+        let pc = interpreter.program_counter();
+        let op = interpreter.contract.bytecode.bytecode().get(pc).copied();
+
+        let depth = interpreter.call_depth() as usize;
+        if self.pending_gas.len() <= depth {
+            self.pending_gas.resize(depth + 1, (0, 0));
+        }
+        self.pending_gas[depth] = (interpreter.gas.remaining(), self.logs.len());
+
+        let op_name = op
+            .and_then(OpCode::new)
+            .map(|op| op.as_str().to_string())
+            .unwrap_or_else(|| format!("UNKNOWN(0x{:02x})", op.unwrap_or_default()));
+
+        let stack = (!self.disable_stack).then(|| {
+            interpreter
+                .stack()
+                .data()
+                .iter()
+                .map(|word| H256::from(word.to_be_bytes::<32>()))
+                .collect()
+        });
+
+        let memory = (!self.disable_memory).then(|| {
+            interpreter
+                .memory
+                .data()
+                .chunks(32)
+                .map(H256::from_slice)
+                .collect()
+        });
+
+        // The slot this step changes, if any; only `SSTORE` mutates storage, and both the
+        // key and the new value are still on the stack at step entry.
+        let storage = (!self.disable_storage)
+            .then(|| {
+                if op == Some(opcode::SSTORE) {
+                    let key = interpreter.stack().peek(0).ok()?;
+                    let value = interpreter.stack().peek(1).ok()?;
+                    Some(BTreeMap::from([(
+                        H256::from(key.to_be_bytes::<32>()),
+                        H256::from(value.to_be_bytes::<32>()),
+                    )]))
+                } else {
+                    Some(BTreeMap::new())
+                }
+            })
+            .flatten();
+
+        self.logs.push(StructLog {
+            pc: pc as u64,
+            op: op_name,
+            gas: interpreter.gas.remaining(),
+            gas_cost: 0,
+            depth: interpreter.call_depth() as u64,
+            stack,
+            memory,
+            storage,
+        });
+
+        InstructionResult::Continue
+    }
+
+    fn step_end(
+        &mut self,
+        interpreter: &mut Interpreter,
+        _data: &mut EVMData<'_, DB>,
+        _eval: InstructionResult,
+    ) -> InstructionResult {
+        let depth = interpreter.call_depth() as usize;
+        if let Some(&(gas_before, index)) = self.pending_gas.get(depth) {
+            if let Some(log) = self.logs.get_mut(index) {
+                log.gas_cost = gas_before.saturating_sub(interpreter.gas.remaining());
+            }
+        }
+
+        InstructionResult::Continue
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use ethers::abi::{Address, Hash, Uint};
+use foundry_evm::utils::{b160_to_h160, ru256_to_u256};
+use revm::primitives::{Account, AccountInfo, B160};
+
+/// Pre/post values for a single account touched by a committing call. A field is `None`
+/// when that part of the account didn't actually change.
+#[derive(Debug, Clone, Default)]
+pub struct AccountDiff {
+    pub balance: Option<(Uint, Uint)>,
+    pub nonce: Option<(u64, u64)>,
+    pub code_hash: Option<(Hash, Hash)>,
+    /// slot -> (pre, post), only for slots whose value actually changed.
+    pub storage: HashMap<Hash, (Uint, Uint)>,
+}
+
+pub type StateDiff = HashMap<Address, AccountDiff>;
+
+/// Builds a [`StateDiff`] from the accounts a committing call touched.
+///
+/// `pre` is the `AccountInfo` snapshot taken from the backend for each touched address
+/// before the call committed. `post` is the `Account`/`StorageSlot` set revm produces for
+/// the committed call; `StorageSlot` already tracks each slot's original and present
+/// value, so no separate pre-snapshot of storage is needed.
+pub fn build_state_diff(
+    pre: &HashMap<Address, AccountInfo>,
+    post: &HashMap<B160, Account>,
+) -> StateDiff {
+    post.iter()
+        .map(|(address, account)| {
+            let address = b160_to_h160(*address);
+            let before = pre.get(&address).cloned().unwrap_or_default();
+
+            let storage = account
+                .storage
+                .iter()
+                .filter(|(_, slot)| slot.previous_or_original_value != slot.present_value)
+                .map(|(slot, value)| {
+                    (
+                        Hash::from(slot.to_be_bytes::<32>()),
+                        (
+                            ru256_to_u256(value.previous_or_original_value),
+                            ru256_to_u256(value.present_value),
+                        ),
+                    )
+                })
+                .collect();
+
+            let diff = AccountDiff {
+                balance: (before.balance != account.info.balance).then(|| {
+                    (
+                        ru256_to_u256(before.balance),
+                        ru256_to_u256(account.info.balance),
+                    )
+                }),
+                nonce: (before.nonce != account.info.nonce)
+                    .then_some((before.nonce, account.info.nonce)),
+                code_hash: (before.code_hash != account.info.code_hash).then(|| {
+                    (
+                        Hash::from(before.code_hash.0),
+                        Hash::from(account.info.code_hash.0),
+                    )
+                }),
+                storage,
+            };
+
+            (address, diff)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foundry_evm::utils::{h160_to_b160, u256_to_ru256};
+    use revm::primitives::StorageSlot;
+
+    fn touched_account(balance: u64, nonce: u64) -> Account {
+        let mut account = Account::new_not_existing();
+        account.info.balance = u256_to_ru256(Uint::from(balance));
+        account.info.nonce = nonce;
+        account
+    }
+
+    #[test]
+    fn reports_balance_change_but_not_unchanged_nonce() {
+        let address = Address::from_low_u64_be(1);
+        let pre = HashMap::from([(
+            address,
+            AccountInfo {
+                balance: u256_to_ru256(Uint::from(100u64)),
+                nonce: 5,
+                ..Default::default()
+            },
+        )]);
+        let post = HashMap::from([(h160_to_b160(address), touched_account(150, 5))]);
+
+        let diff = build_state_diff(&pre, &post);
+        let account_diff = &diff[&address];
+
+        assert_eq!(
+            account_diff.balance,
+            Some((Uint::from(100u64), Uint::from(150u64)))
+        );
+        assert_eq!(account_diff.nonce, None);
+    }
+
+    #[test]
+    fn drops_unchanged_storage_slots_and_keeps_changed_ones() {
+        let address = Address::from_low_u64_be(2);
+        let unchanged_slot = u256_to_ru256(Uint::from(7u64));
+        let changed_slot = u256_to_ru256(Uint::from(8u64));
+
+        let mut account = touched_account(0, 0);
+        account.storage.insert(
+            unchanged_slot,
+            StorageSlot {
+                previous_or_original_value: u256_to_ru256(Uint::from(1u64)),
+                present_value: u256_to_ru256(Uint::from(1u64)),
+            },
+        );
+        account.storage.insert(
+            changed_slot,
+            StorageSlot {
+                previous_or_original_value: u256_to_ru256(Uint::from(1u64)),
+                present_value: u256_to_ru256(Uint::from(2u64)),
+            },
+        );
+
+        let post = HashMap::from([(h160_to_b160(address), account)]);
+        let diff = build_state_diff(&HashMap::new(), &post);
+        let storage = &diff[&address].storage;
+
+        assert_eq!(storage.len(), 1);
+        assert_eq!(
+            storage[&Hash::from(changed_slot.to_be_bytes::<32>())],
+            (Uint::from(1u64), Uint::from(2u64))
+        );
+    }
+
+    #[test]
+    fn missing_pre_snapshot_defaults_to_zero_values() {
+        let address = Address::from_low_u64_be(3);
+        let post = HashMap::from([(h160_to_b160(address), touched_account(10, 1))]);
+
+        let diff = build_state_diff(&HashMap::new(), &post);
+        let account_diff = &diff[&address];
+
+        assert_eq!(account_diff.balance, Some((Uint::zero(), Uint::from(10u64))));
+        assert_eq!(account_diff.nonce, Some((0, 1)));
+    }
+}